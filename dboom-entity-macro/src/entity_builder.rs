@@ -1,19 +1,103 @@
 use proc_macro::TokenStream;
-use proc_macro2::{TokenStream as TokenStream2};
-use quote::{quote};
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
 use syn::{parse_macro_input, DeriveInput};
 
 use super::props::*;
 
-pub struct EntityBuilder {
-}
+pub struct EntityBuilder {}
 
 impl EntityBuilder {
     pub fn new() -> Self {
-        EntityBuilder{}
+        EntityBuilder {}
+    }
+
+    /// Pooled-`DB` entry point for [`Self::build_save_tx_fn`]'s generic body.
+    fn build_save_fn(&self, _props: &Props) -> TokenStream2 {
+        quote! {
+            async fn save(&mut self, db: &dboom::db::DB) -> dboom::db::DBResult<bool> {
+                self.save_tx(db).await
+            }
+        }
     }
 
-    fn build_save_fn(&self, props: &Props) -> TokenStream2 {
+    fn build_save_many_fn(&self, props: &Props) -> TokenStream2 {
+        let table_name = props.get_table_name();
+        let fields_plain_names = props.get_fields_plain_names();
+        let fields_per_row = fields_plain_names.len();
+
+        let primary_key = props.get_primary_key_field().unwrap();
+        let primary_key_ident = &primary_key.ident;
+        let primary_key_type = &primary_key.ty;
+
+        quote! {
+            async fn save_many(db: &dboom::db::DB, items: &mut [Self]) -> dboom::db::DBResult<()> {
+                const FIELDS_PER_ROW: usize = #fields_per_row;
+                // Postgres caps a statement at 65535 bind parameters.
+                const MAX_PARAMS: usize = 65535;
+
+                if items.is_empty() {
+                    return Ok(());
+                }
+
+                // No plain (non-primary-key) column to bulk-insert into.
+                if FIELDS_PER_ROW == 0 {
+                    return Err(dboom::db::Error::Other);
+                }
+
+                let primary_key_default: #primary_key_type = Default::default();
+                if items.iter().any(|item| item.#primary_key_ident != primary_key_default) {
+                    return Err(dboom::db::Error::Other);
+                }
+
+                let chunk_len = std::cmp::max(1, MAX_PARAMS / FIELDS_PER_ROW);
+
+                for chunk in items.chunks_mut(chunk_len) {
+                    let mut placeholders: Vec<String> = Vec::with_capacity(chunk.len());
+                    let mut params: Vec<&(dyn dboom::db_types::ToSql + Sync)> =
+                        Vec::with_capacity(chunk.len() * FIELDS_PER_ROW);
+
+                    for (row_index, item) in chunk.iter().enumerate() {
+                        let base = row_index * FIELDS_PER_ROW;
+                        let row_placeholders: Vec<String> = (1..=FIELDS_PER_ROW)
+                            .map(|i| format!("${}", base + i))
+                            .collect();
+                        placeholders.push(format!("({})", row_placeholders.join(",")));
+
+                        #(params.push(&item.#fields_plain_names);)*
+                    }
+
+                    let query_str = format!(
+                        concat!(
+                            "INSERT INTO ",
+                            #table_name,
+                            " (",
+                            stringify!(#(#fields_plain_names),*),
+                            ") VALUES {} RETURNING ",
+                            stringify!(#primary_key_ident)
+                        ),
+                        placeholders.join(",")
+                    );
+
+                    let rows = db.query(&query_str, &params).await?;
+
+                    for (item, row) in chunk.iter_mut().zip(rows.iter()) {
+                        item.#primary_key_ident =
+                            row.get::<&str, #primary_key_type>(stringify!(#primary_key_ident));
+                    }
+                }
+
+                Ok(())
+            }
+        }
+    }
+
+    /// Generic-over-`Executor` body shared by the pooled `Entity::save`
+    /// (via [`Self::build_save_fn`]) and transaction callers. Runs equally
+    /// well over a pooled `DB` or a borrowed `Transaction`, and is exposed
+    /// as an inherent `save_tx` method (outside the `Entity` trait, since
+    /// `Entity`'s `save` signature is pinned to `&DB`).
+    fn build_save_tx_fn(&self, props: &Props) -> TokenStream2 {
         let table_name = props.get_table_name();
         let fields_plain_names = props.get_fields_plain_names();
         let fields_plain_numbered = props.get_fields_plain_numbered();
@@ -24,13 +108,13 @@ impl EntityBuilder {
         let primary_key_type = &primary_key.ty;
 
         quote! {
-            async fn save(&mut self, db: &dboom::db::DB) -> dboom::db::DBResult<bool> {
+            pub async fn save_tx<E: dboom::db::Executor>(&mut self, executor: &E) -> dboom::db::DBResult<bool> {
                 let mut creating = false;
                 let primary_key_default: #primary_key_type = Default::default();
                 let _result = match self.#primary_key_ident {
                     v if self.#primary_key_ident == primary_key_default => {
                         creating = true;
-                        let rows = db.query(
+                        let rows = executor.query(
                             concat!(
                                 "INSERT INTO ",
                                 #table_name,
@@ -49,7 +133,7 @@ impl EntityBuilder {
                         1
                     },
                     id => {
-                        db.execute(
+                        executor.execute(
                             concat!(
                                 "UPDATE ",
                                 #table_name,
@@ -70,6 +154,45 @@ impl EntityBuilder {
         }
     }
 
+    /// Generic-over-`Executor` body shared by the pooled `Entity::delete`
+    /// (via [`Self::build_delete_fn`]) and transaction callers.
+    fn build_delete_tx_fn(&self, props: &Props) -> TokenStream2 {
+        let primary_key_ident = &props.get_primary_key_field().unwrap().ident;
+        let table_name = props.get_table_name();
+        quote! {
+            pub async fn delete_tx<E: dboom::db::Executor>(&mut self, executor: &E) -> dboom::db::DBResult<bool> {
+                if self.#primary_key_ident == Default::default() {
+                    return Ok(false);
+                }
+
+                let condition = format!("{} = $1", stringify!(#primary_key_ident));
+                let query_str = format!("DELETE FROM {} WHERE {}", #table_name, condition);
+                match executor.execute(&query_str, &[&self.#primary_key_ident]).await? {
+                    0 => Ok(false),
+                    _ => {
+                        self.#primary_key_ident = 0;
+                        Ok(true)
+                    },
+                }
+            }
+        }
+    }
+
+    /// Generic-over-`Executor` body shared by the pooled `Entity::find`
+    /// (via [`Self::build_find_fn`]) and transaction callers.
+    fn build_find_tx_fn(&self, props: &Props) -> TokenStream2 {
+        let name = props.get_name();
+        let table_name = props.get_table_name();
+        quote! {
+            pub async fn find_tx<E: dboom::db::Executor>(executor: &E, condition: &str, params: &'_ [&'_ (dyn dboom::db_types::ToSql + Sync)]) -> dboom::db::DBResult<Vec<#name>> {
+                let query_str = format!("SELECT * FROM {} WHERE {}", #table_name, condition);
+                let rows = executor.query(&query_str, params).await?;
+                let results: Vec<#name> = rows.iter().map(|row| Self::from_row(row)).collect();
+                Ok(results)
+            }
+        }
+    }
+
     fn build_from_row_fn(&self, props: &Props) -> TokenStream2 {
         let fields_all_names = props.get_fields_all_names();
         let fields_all_types = props.get_fields_all_types();
@@ -100,15 +223,11 @@ impl EntityBuilder {
         }
     }
 
-    fn build_find_fn(&self, props: &Props) -> TokenStream2 {
-        let name = props.get_name();
-        let table_name = props.get_table_name();
+    /// Pooled-`DB` entry point for [`Self::build_find_tx_fn`]'s generic body.
+    fn build_find_fn(&self, _props: &Props) -> TokenStream2 {
         quote! {
-            async fn find(db: &dboom::db::DB, condition: &str, params: &'_ [&'_ (dyn dboom::db_types::ToSql + Sync)]) -> dboom::db::DBResult<Vec<#name>> {
-                let query_str = format!("SELECT * FROM {} WHERE {}", #table_name, condition);
-                let rows = db.query(&query_str, params).await?;
-                let results: Vec<#name> = rows.iter().map(|row| Self::from_row(row)).collect();
-                Ok(results)
+            async fn find(db: &dboom::db::DB, condition: &str, params: &'_ [&'_ (dyn dboom::db_types::ToSql + Sync)]) -> dboom::db::DBResult<Vec<Self>> {
+                Self::find_tx(db, condition, params).await
             }
         }
     }
@@ -129,24 +248,11 @@ impl EntityBuilder {
         }
     }
 
-    fn build_delete_fn(&self, props: &Props) -> TokenStream2 {
-        let primary_key_ident = &props.get_primary_key_field().unwrap().ident;
-        let table_name = props.get_table_name();
+    /// Pooled-`DB` entry point for [`Self::build_delete_tx_fn`]'s generic body.
+    fn build_delete_fn(&self, _props: &Props) -> TokenStream2 {
         quote! {
             async fn delete(&mut self, db: &dboom::db::DB) -> dboom::db::DBResult<bool> {
-                if self.#primary_key_ident == Default::default() {
-                    return Ok(false);
-                }
-
-                let condition = format!("{} = $1", stringify!(#primary_key_ident));
-                let query_str = format!("DELETE FROM {} WHERE {}", #table_name, condition);
-                match db.execute(&query_str, &[&self.#primary_key_ident]).await? {
-                    0 => Ok(false),
-                    _ => {
-                        self.#primary_key_ident = 0;
-                        Ok(true)
-                    },
-                }
+                self.delete_tx(db).await
             }
         }
     }
@@ -163,12 +269,17 @@ impl EntityBuilder {
         eprintln!("{:#?}", props.get_fields_all_types());
 
         let save_fn = self.build_save_fn(&props);
+        let save_many_fn = self.build_save_many_fn(&props);
         let delete_fn = self.build_delete_fn(&props);
         let from_row_fn = self.build_from_row_fn(&props);
         let create_migration_fn = self.build_create_migration_fn(&props);
         let find_fn = self.build_find_fn(&props);
         let first_fn = self.build_first_fn(&props);
 
+        let save_tx_fn = self.build_save_tx_fn(&props);
+        let delete_tx_fn = self.build_delete_tx_fn(&props);
+        let find_tx_fn = self.build_find_tx_fn(&props);
+
         let name = props.get_name();
 
         let expanded = quote! {
@@ -178,6 +289,8 @@ impl EntityBuilder {
             impl dboom::entity::Entity for #name {
                 #save_fn
 
+                #save_many_fn
+
                 #delete_fn
 
                 #from_row_fn
@@ -188,6 +301,14 @@ impl EntityBuilder {
 
                 #first_fn
             }
+
+            impl #name {
+                #save_tx_fn
+
+                #delete_tx_fn
+
+                #find_tx_fn
+            }
         };
 
         // Hand the output tokens back to the compiler
@@ -197,4 +318,4 @@ impl EntityBuilder {
 
         r
     }
-}
\ No newline at end of file
+}
@@ -5,18 +5,55 @@ use openssl::ssl::{SslConnector, SslMethod};
 use postgres_openssl::MakeTlsConnector;
 use refinery::{Report, Runner};
 use std::str::FromStr;
+use tokio::sync::mpsc;
+use tokio_postgres_rustls::MakeRustlsConnect;
 
 use super::super::migration::Migration;
 use super::error::*;
+use super::executor::Executor;
+use super::notify::{spawn_notification_listener, NotificationStream};
+use super::retry::{self, RetryPolicy};
+use super::tls::{build_rustls_connector, TlsConfig};
+use super::transaction::{Transaction, TransactionClient};
 
 use barrel::backend::Pg;
 use tokio_postgres::{
+    config::SslMode,
     row::Row,
     tls::{MakeTlsConnect, TlsConnect},
     types::ToSql,
     Client, Config, NoTls, Socket,
 };
 
+/// Capacity of the channel buffering notifications between the dedicated
+/// listener connection's background task and the stream the caller polls.
+const NOTIFICATION_CHANNEL_SIZE: usize = 128;
+
+/// Name of the table refinery uses to track applied migrations.
+const REFINERY_SCHEMA_HISTORY_TABLE: &str = "refinery_schema_history";
+
+/// Name of the table `DB::migrate_tables` uses to record each applied
+/// migration's down SQL, keyed by its refinery version, so `DB::rollback`
+/// can look it up explicitly instead of assuming the caller's migration
+/// slice still lines up by position.
+const OXIDIZER_MIGRATIONS_TABLE: &str = "oxidizer_migrations";
+
+/// A connection-level failure is never safe to retry on the same pooled
+/// connection — hand `client` back to mobc as broken (rather than letting
+/// `Drop` return it to the pool looking healthy) so the next attempt is
+/// guaranteed a fresh one, and wrap `err` as a `DB` [`Error`].
+fn invalidate_on_retryable<M: Manager>(
+    client: mobc::Connection<M>,
+    err: tokio_postgres::Error,
+) -> Error {
+    if retry::is_retryable(&err) {
+        client.invalidate();
+    }
+
+    Error::PostgresError(err)
+}
+
+#[derive(Clone)]
 pub struct ConnectionManager<Tls> {
     config: Config,
     tls: Tls,
@@ -28,6 +65,30 @@ impl<Tls> ConnectionManager<Tls> {
     }
 }
 
+impl<Tls> ConnectionManager<Tls>
+where
+    Tls: MakeTlsConnect<Socket> + Clone + Send + Sync + 'static,
+    <Tls as MakeTlsConnect<Socket>>::Stream: Send + Sync,
+    <Tls as MakeTlsConnect<Socket>>::TlsConnect: Send,
+    <<Tls as MakeTlsConnect<Socket>>::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    /// Opens a connection that is *not* handed to mobc, so the caller can
+    /// drive its `Connection` future directly (e.g. to observe `LISTEN`
+    /// notifications rather than discarding them).
+    async fn connect_dedicated(
+        &self,
+    ) -> Result<
+        (
+            Client,
+            tokio_postgres::Connection<Socket, <Tls as MakeTlsConnect<Socket>>::Stream>,
+        ),
+        tokio_postgres::Error,
+    > {
+        let tls = self.tls.clone();
+        self.config.connect(tls).await
+    }
+}
+
 #[async_trait]
 impl<Tls> Manager for ConnectionManager<Tls>
 where
@@ -54,45 +115,108 @@ where
 
 #[derive(Clone)]
 enum ConnectionPool {
-    TLS(Pool<ConnectionManager<MakeTlsConnector>>),
-    NoTLS(Pool<ConnectionManager<NoTls>>),
+    TLS(
+        Pool<ConnectionManager<MakeTlsConnector>>,
+        ConnectionManager<MakeTlsConnector>,
+    ),
+    Rustls(
+        Pool<ConnectionManager<MakeRustlsConnect>>,
+        ConnectionManager<MakeRustlsConnect>,
+    ),
+    NoTLS(Pool<ConnectionManager<NoTls>>, ConnectionManager<NoTls>),
 }
 
 #[derive(Clone)]
 pub struct DB {
     pool: ConnectionPool,
+    retry_policy: RetryPolicy,
 }
 
 impl DB {
     pub async fn connect(uri: &str, max_open: u64, ca_file: Option<&str>) -> Result<Self, Error> {
-        if let Some(ca_file) = ca_file {
-            let mut builder =
-                SslConnector::builder(SslMethod::tls()).map_err(|err| Error::OpensslError(err))?;
-
-            builder
-                .set_ca_file(ca_file)
-                .map_err(|err| Error::OpensslError(err))?;
-
-            let connector = MakeTlsConnector::new(builder.build());
-            let config =
-                tokio_postgres::Config::from_str(uri).map_err(|err| Error::PostgresError(err))?;
-            let manager = ConnectionManager::new(config, connector);
-
-            Ok(DB {
-                pool: ConnectionPool::TLS(Pool::builder().max_open(max_open).build(manager)),
-            })
-        } else {
-            let config =
-                tokio_postgres::Config::from_str(uri).map_err(|err| Error::PostgresError(err))?;
-
-            let manager = ConnectionManager::new(config, NoTls);
+        let tls = match ca_file {
+            Some(ca_file) => TlsConfig::OpenSsl {
+                ca_file: ca_file.to_string(),
+            },
+            None => TlsConfig::Disabled,
+        };
+
+        Self::connect_with(uri, max_open, tls).await
+    }
 
-            Ok(DB {
-                pool: ConnectionPool::NoTLS(Pool::builder().max_open(max_open).build(manager)),
-            })
+    /// Connects to Postgres, choosing the TLS backend via `tls`.
+    ///
+    /// `sslmode` in `uri` is honoured: `sslmode=disable` always connects
+    /// without TLS regardless of `tls`, matching how `libpq`-based clients
+    /// treat an explicit opt-out.
+    pub async fn connect_with(uri: &str, max_open: u64, tls: TlsConfig) -> Result<Self, Error> {
+        let config =
+            tokio_postgres::Config::from_str(uri).map_err(|err| Error::PostgresError(err))?;
+
+        let tls = if config.get_ssl_mode() == SslMode::Disable {
+            TlsConfig::Disabled
+        } else {
+            tls
+        };
+
+        match tls {
+            TlsConfig::OpenSsl { ca_file } => {
+                let mut builder = SslConnector::builder(SslMethod::tls())
+                    .map_err(|err| Error::OpensslError(err))?;
+
+                builder
+                    .set_ca_file(ca_file)
+                    .map_err(|err| Error::OpensslError(err))?;
+
+                let connector = MakeTlsConnector::new(builder.build());
+                let manager = ConnectionManager::new(config, connector);
+
+                Ok(DB {
+                    pool: ConnectionPool::TLS(
+                        Pool::builder().max_open(max_open).build(manager.clone()),
+                        manager,
+                    ),
+                    retry_policy: RetryPolicy::default(),
+                })
+            }
+            TlsConfig::Rustls {
+                ca_pem,
+                client_cert,
+                client_key,
+            } => {
+                let connector =
+                    build_rustls_connector(&ca_pem, client_cert.as_deref(), client_key.as_deref())?;
+                let manager = ConnectionManager::new(config, connector);
+
+                Ok(DB {
+                    pool: ConnectionPool::Rustls(
+                        Pool::builder().max_open(max_open).build(manager.clone()),
+                        manager,
+                    ),
+                    retry_policy: RetryPolicy::default(),
+                })
+            }
+            TlsConfig::Disabled => {
+                let manager = ConnectionManager::new(config, NoTls);
+
+                Ok(DB {
+                    pool: ConnectionPool::NoTLS(
+                        Pool::builder().max_open(max_open).build(manager.clone()),
+                        manager,
+                    ),
+                    retry_policy: RetryPolicy::default(),
+                })
+            }
         }
     }
 
+    /// Opts into retrying `execute`/`query` on transient, connection-level
+    /// errors according to `policy` instead of failing on the first one.
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
     pub async fn create(
         &self,
         query: &str,
@@ -105,33 +229,54 @@ impl DB {
         &self,
         query: &str,
         params: &'_ [&'_ (dyn ToSql + Sync)],
+    ) -> Result<u64, Error> {
+        retry::retry(&self.retry_policy, || self.execute_once(query, params)).await
+    }
+
+    async fn execute_once(
+        &self,
+        query: &str,
+        params: &'_ [&'_ (dyn ToSql + Sync)],
     ) -> Result<u64, Error> {
         match &self.pool {
-            ConnectionPool::TLS(pool) => {
+            ConnectionPool::TLS(pool, _) => {
                 let client = pool.get().await.map_err(|err| Error::MobcError(err))?;
 
-                let insert = client
-                    .prepare(query)
-                    .await
-                    .map_err(|err| Error::PostgresError(err))?;
+                let insert = match client.prepare(query).await {
+                    Ok(stmt) => stmt,
+                    Err(err) => return Err(invalidate_on_retryable(client, err)),
+                };
 
                 client
                     .execute(&insert, params)
                     .await
-                    .map_err(|err| Error::PostgresError(err))
+                    .map_err(|err| invalidate_on_retryable(client, err))
             }
-            ConnectionPool::NoTLS(pool) => {
+            ConnectionPool::Rustls(pool, _) => {
                 let client = pool.get().await.map_err(|err| Error::MobcError(err))?;
 
-                let insert = client
-                    .prepare(query)
+                let insert = match client.prepare(query).await {
+                    Ok(stmt) => stmt,
+                    Err(err) => return Err(invalidate_on_retryable(client, err)),
+                };
+
+                client
+                    .execute(&insert, params)
                     .await
-                    .map_err(|err| Error::PostgresError(err))?;
+                    .map_err(|err| invalidate_on_retryable(client, err))
+            }
+            ConnectionPool::NoTLS(pool, _) => {
+                let client = pool.get().await.map_err(|err| Error::MobcError(err))?;
+
+                let insert = match client.prepare(query).await {
+                    Ok(stmt) => stmt,
+                    Err(err) => return Err(invalidate_on_retryable(client, err)),
+                };
 
                 client
                     .execute(&insert, params)
                     .await
-                    .map_err(|err| Error::PostgresError(err))
+                    .map_err(|err| invalidate_on_retryable(client, err))
             }
         }
     }
@@ -140,69 +285,165 @@ impl DB {
         &self,
         query: &str,
         params: &'_ [&'_ (dyn ToSql + Sync)],
+    ) -> Result<Vec<Row>, Error> {
+        retry::retry(&self.retry_policy, || self.query_once(query, params)).await
+    }
+
+    async fn query_once(
+        &self,
+        query: &str,
+        params: &'_ [&'_ (dyn ToSql + Sync)],
     ) -> Result<Vec<Row>, Error> {
         match &self.pool {
-            ConnectionPool::TLS(pool) => {
+            ConnectionPool::TLS(pool, _) => {
                 let client = pool.get().await.map_err(|err| Error::MobcError(err))?;
 
-                let insert = client
-                    .prepare(query)
-                    .await
-                    .map_err(|err| Error::PostgresError(err))?;
+                let insert = match client.prepare(query).await {
+                    Ok(stmt) => stmt,
+                    Err(err) => return Err(invalidate_on_retryable(client, err)),
+                };
 
                 client
                     .query(&insert, params)
                     .await
-                    .map_err(|err| Error::PostgresError(err))
+                    .map_err(|err| invalidate_on_retryable(client, err))
             }
-            ConnectionPool::NoTLS(pool) => {
+            ConnectionPool::Rustls(pool, _) => {
                 let client = pool.get().await.map_err(|err| Error::MobcError(err))?;
 
-                let insert = client
-                    .prepare(query)
+                let insert = match client.prepare(query).await {
+                    Ok(stmt) => stmt,
+                    Err(err) => return Err(invalidate_on_retryable(client, err)),
+                };
+
+                client
+                    .query(&insert, params)
                     .await
-                    .map_err(|err| Error::PostgresError(err))?;
+                    .map_err(|err| invalidate_on_retryable(client, err))
+            }
+            ConnectionPool::NoTLS(pool, _) => {
+                let client = pool.get().await.map_err(|err| Error::MobcError(err))?;
+
+                let insert = match client.prepare(query).await {
+                    Ok(stmt) => stmt,
+                    Err(err) => return Err(invalidate_on_retryable(client, err)),
+                };
 
                 client
                     .query(&insert, params)
                     .await
-                    .map_err(|err| Error::PostgresError(err))
+                    .map_err(|err| invalidate_on_retryable(client, err))
             }
         }
     }
 
-    pub async fn migrate_tables(&self, ms: &[Migration]) -> Result<Report, Error> {
-        let ref_migrations: Vec<refinery::Migration> = ms
-            .as_ref()
-            .iter()
-            .enumerate()
-            .filter_map(|(i, m)| {
-                let sql = m.raw.make::<Pg>();
+    /// Runs `query` through the connection's `batch_execute`, which (unlike
+    /// [`Self::execute`]) accepts multiple `;`-separated statements in one
+    /// call. Used to create `OXIDIZER_MIGRATIONS_TABLE`; `DB::rollback`
+    /// runs down-migration SQL through [`Transaction::batch_execute`]
+    /// instead, on the transaction's own connection.
+    pub async fn batch_execute(&self, query: &str) -> Result<(), Error> {
+        retry::retry(&self.retry_policy, || self.batch_execute_once(query)).await
+    }
+
+    async fn batch_execute_once(&self, query: &str) -> Result<(), Error> {
+        match &self.pool {
+            ConnectionPool::TLS(pool, _) => {
+                let client = pool.get().await.map_err(|err| Error::MobcError(err))?;
+                client
+                    .batch_execute(query)
+                    .await
+                    .map_err(|err| invalidate_on_retryable(client, err))
+            }
+            ConnectionPool::Rustls(pool, _) => {
+                let client = pool.get().await.map_err(|err| Error::MobcError(err))?;
+                client
+                    .batch_execute(query)
+                    .await
+                    .map_err(|err| invalidate_on_retryable(client, err))
+            }
+            ConnectionPool::NoTLS(pool, _) => {
+                let client = pool.get().await.map_err(|err| Error::MobcError(err))?;
+                client
+                    .batch_execute(query)
+                    .await
+                    .map_err(|err| invalidate_on_retryable(client, err))
+            }
+        }
+    }
 
-                let name = format!("V{}__{}.rs", i, m.name);
+    /// Runs every migration in `ms` that refinery hasn't yet applied, in the
+    /// order given. The `i`-th migration becomes refinery version `i`, and
+    /// that version is also what `DB::rollback` later uses to look up the
+    /// matching `down` SQL — recorded below rather than re-derived from
+    /// `ms`'s position, so rollback can't be fed a different slice and drop
+    /// the wrong table.
+    pub async fn migrate_tables(&self, ms: &[Migration]) -> Result<Report, Error> {
+        let mut ref_migrations: Vec<refinery::Migration> = Vec::with_capacity(ms.len());
+        let mut downs: Vec<(i32, String)> = Vec::with_capacity(ms.len());
 
-                let migration = refinery::Migration::unapplied(&name, &sql).unwrap();
+        for (i, m) in ms.iter().enumerate() {
+            let up_sql = m.up.make::<Pg>();
+            let name = format!("V{}__{}.rs", i, m.name);
 
-                Some(migration)
-            })
-            .collect();
+            ref_migrations.push(refinery::Migration::unapplied(&name, &up_sql).unwrap());
+            downs.push((i as i32, m.down.make::<Pg>()));
+        }
 
         let runner = refinery::Runner::new(&ref_migrations);
 
-        self.migrate(runner).await
+        let report = self.migrate(runner).await?;
+
+        self.record_down_migrations(&downs).await?;
+
+        Ok(report)
+    }
+
+    /// Upserts each `(version, down_sql)` pair into
+    /// `OXIDIZER_MIGRATIONS_TABLE`, creating it on first use. Called after
+    /// every `migrate_tables`, whether or not a given version was newly
+    /// applied this run, so the table always reflects the down SQL for the
+    /// full migration set currently passed in.
+    async fn record_down_migrations(&self, downs: &[(i32, String)]) -> Result<(), Error> {
+        self.batch_execute(&format!(
+            "CREATE TABLE IF NOT EXISTS {} (version INT4 PRIMARY KEY, down_sql TEXT NOT NULL)",
+            OXIDIZER_MIGRATIONS_TABLE
+        ))
+        .await?;
+
+        for (version, down_sql) in downs {
+            self.execute(
+                &format!(
+                    "INSERT INTO {} (version, down_sql) VALUES ($1, $2) \
+                     ON CONFLICT (version) DO UPDATE SET down_sql = EXCLUDED.down_sql",
+                    OXIDIZER_MIGRATIONS_TABLE
+                ),
+                &[version, down_sql],
+            )
+            .await?;
+        }
+
+        Ok(())
     }
 
     pub async fn migrate(&self, runner: Runner) -> Result<Report, Error> {
         let runner = runner.set_abort_divergent(false);
         match &self.pool {
-            ConnectionPool::TLS(pool) => {
+            ConnectionPool::TLS(pool, _) => {
+                let mut client = pool.get().await.map_err(|err| Error::MobcError(err))?;
+                Ok(runner
+                    .run_async(&mut *client)
+                    .await
+                    .map_err(|err| Error::RefineryError(err))?)
+            }
+            ConnectionPool::Rustls(pool, _) => {
                 let mut client = pool.get().await.map_err(|err| Error::MobcError(err))?;
                 Ok(runner
                     .run_async(&mut *client)
                     .await
                     .map_err(|err| Error::RefineryError(err))?)
             }
-            ConnectionPool::NoTLS(pool) => {
+            ConnectionPool::NoTLS(pool, _) => {
                 let mut client = pool.get().await.map_err(|err| Error::MobcError(err))?;
                 Ok(runner
                     .run_async(&mut *client)
@@ -211,4 +452,158 @@ impl DB {
             }
         }
     }
+
+    /// Undoes the last `steps` migrations that refinery recorded as
+    /// applied, running each one's `down` SQL (as recorded by
+    /// `migrate_tables` in `OXIDIZER_MIGRATIONS_TABLE`, keyed by version)
+    /// and removing its row from refinery's own tracking table so the two
+    /// stay in sync.
+    ///
+    /// Targeting rows from `refinery_schema_history` (rather than taking a
+    /// migration list from the caller) means rollback only ever touches
+    /// migrations that were actually applied to this database, and can't
+    /// be pointed at the wrong migration by a reordered or partial slice.
+    /// Each version's down SQL and history row are applied together inside
+    /// one transaction, so a failure partway through can't leave the schema
+    /// and refinery's tracking table disagreeing about what's applied.
+    pub async fn rollback(&self, steps: usize) -> Result<(), Error> {
+        let applied = self
+            .query(
+                &format!(
+                    "SELECT version FROM {} ORDER BY version DESC LIMIT $1",
+                    REFINERY_SCHEMA_HISTORY_TABLE
+                ),
+                &[&(steps as i64)],
+            )
+            .await?;
+
+        for row in applied {
+            let version: i32 = row.get("version");
+
+            let tx = self.transaction().await?;
+
+            let down_rows = tx
+                .query(
+                    &format!(
+                        "SELECT down_sql FROM {} WHERE version = $1",
+                        OXIDIZER_MIGRATIONS_TABLE
+                    ),
+                    &[&version],
+                )
+                .await?;
+            let down_sql: String = down_rows.first().ok_or(Error::Other)?.get("down_sql");
+
+            tx.batch_execute(&down_sql).await?;
+
+            tx.execute(
+                &format!(
+                    "DELETE FROM {} WHERE version = $1",
+                    REFINERY_SCHEMA_HISTORY_TABLE
+                ),
+                &[&version],
+            )
+            .await?;
+
+            tx.commit().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Subscribes to a Postgres `NOTIFY` channel.
+    ///
+    /// This opens a dedicated connection outside the mobc pool and keeps it
+    /// alive for as long as the returned [`NotificationStream`] is held;
+    /// dropping the stream tears the connection down.
+    pub async fn listen(&self, channel: &str) -> Result<NotificationStream, Error> {
+        let (tx, rx) = mpsc::channel(NOTIFICATION_CHANNEL_SIZE);
+
+        match &self.pool {
+            ConnectionPool::TLS(_, manager) => {
+                let (client, connection) = manager
+                    .connect_dedicated()
+                    .await
+                    .map_err(|err| Error::PostgresError(err))?;
+
+                let handle = spawn_notification_listener(connection, tx);
+
+                client
+                    .execute(&format!("LISTEN {}", channel), &[])
+                    .await
+                    .map_err(|err| Error::PostgresError(err))?;
+
+                Ok(NotificationStream::new(rx, handle, client))
+            }
+            ConnectionPool::Rustls(_, manager) => {
+                let (client, connection) = manager
+                    .connect_dedicated()
+                    .await
+                    .map_err(|err| Error::PostgresError(err))?;
+
+                let handle = spawn_notification_listener(connection, tx);
+
+                client
+                    .execute(&format!("LISTEN {}", channel), &[])
+                    .await
+                    .map_err(|err| Error::PostgresError(err))?;
+
+                Ok(NotificationStream::new(rx, handle, client))
+            }
+            ConnectionPool::NoTLS(_, manager) => {
+                let (client, connection) = manager
+                    .connect_dedicated()
+                    .await
+                    .map_err(|err| Error::PostgresError(err))?;
+
+                let handle = spawn_notification_listener(connection, tx);
+
+                client
+                    .execute(&format!("LISTEN {}", channel), &[])
+                    .await
+                    .map_err(|err| Error::PostgresError(err))?;
+
+                Ok(NotificationStream::new(rx, handle, client))
+            }
+        }
+    }
+
+    /// Sends a Postgres `NOTIFY` to `channel` via `pg_notify`, so listeners
+    /// (including those registered with [`DB::listen`]) receive `payload`.
+    pub async fn notify(&self, channel: &str, payload: &str) -> Result<(), Error> {
+        self.execute("SELECT pg_notify($1, $2)", &[&channel, &payload])
+            .await?;
+
+        Ok(())
+    }
+
+    /// Checks a single connection out of the pool for the duration of a
+    /// `BEGIN`/`COMMIT`/`ROLLBACK` transaction, so multiple `save`/`delete`
+    /// calls made through it (via the generated `_tx` Entity methods) land
+    /// atomically.
+    pub async fn transaction(&self) -> Result<Transaction, Error> {
+        let client = match &self.pool {
+            ConnectionPool::TLS(pool, _) => {
+                TransactionClient::TLS(pool.get().await.map_err(|err| Error::MobcError(err))?)
+            }
+            ConnectionPool::Rustls(pool, _) => {
+                TransactionClient::Rustls(pool.get().await.map_err(|err| Error::MobcError(err))?)
+            }
+            ConnectionPool::NoTLS(pool, _) => {
+                TransactionClient::NoTLS(pool.get().await.map_err(|err| Error::MobcError(err))?)
+            }
+        };
+
+        Transaction::begin(client).await
+    }
+}
+
+#[async_trait]
+impl Executor for DB {
+    async fn execute(&self, query: &str, params: &'_ [&'_ (dyn ToSql + Sync)]) -> DBResult<u64> {
+        DB::execute(self, query, params).await
+    }
+
+    async fn query(&self, query: &str, params: &'_ [&'_ (dyn ToSql + Sync)]) -> DBResult<Vec<Row>> {
+        DB::query(self, query, params).await
+    }
 }
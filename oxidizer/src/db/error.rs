@@ -0,0 +1,28 @@
+use std::fmt;
+
+pub type DBResult<T> = Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    PostgresError(tokio_postgres::Error),
+    MobcError(mobc::Error<tokio_postgres::Error>),
+    RefineryError(refinery::Error),
+    OpensslError(openssl::error::ErrorStack),
+    TlsError(String),
+    Other,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::PostgresError(err) => write!(f, "postgres error: {}", err),
+            Error::MobcError(err) => write!(f, "mobc error: {}", err),
+            Error::RefineryError(err) => write!(f, "refinery error: {}", err),
+            Error::OpensslError(err) => write!(f, "openssl error: {}", err),
+            Error::TlsError(msg) => write!(f, "tls error: {}", msg),
+            Error::Other => write!(f, "unknown error"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
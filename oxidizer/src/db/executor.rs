@@ -0,0 +1,18 @@
+use async_trait::async_trait;
+use tokio_postgres::{row::Row, types::ToSql};
+
+use super::error::DBResult;
+
+/// Runs queries against either a pooled [`DB`](super::DB) connection or a
+/// single connection borrowed from a [`Transaction`](super::Transaction).
+///
+/// `#[derive(Entity)]` generates the SQL-building logic of `save`, `delete`
+/// and `find` once against this trait, then exposes it as both the plain
+/// (pooled) and `_tx` (transaction-scoped) methods. `first` and `save_many`
+/// run only against a pooled `DB` and have no `_tx` variant.
+#[async_trait]
+pub trait Executor: Send + Sync {
+    async fn execute(&self, query: &str, params: &'_ [&'_ (dyn ToSql + Sync)]) -> DBResult<u64>;
+
+    async fn query(&self, query: &str, params: &'_ [&'_ (dyn ToSql + Sync)]) -> DBResult<Vec<Row>>;
+}
@@ -0,0 +1,15 @@
+mod db;
+mod error;
+mod executor;
+mod notify;
+mod retry;
+mod tls;
+mod transaction;
+
+pub use db::{ConnectionManager, DB};
+pub use error::{DBResult, Error};
+pub use executor::Executor;
+pub use notify::{Notification, NotificationStream};
+pub use retry::RetryPolicy;
+pub use tls::TlsConfig;
+pub use transaction::Transaction;
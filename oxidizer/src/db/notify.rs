@@ -0,0 +1,95 @@
+use futures::stream::{Stream, StreamExt};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio_postgres::{AsyncMessage, Client};
+
+/// A single `NOTIFY` delivered on a channel the caller is listening on.
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub process_id: i32,
+    pub channel: String,
+    pub payload: String,
+}
+
+impl From<tokio_postgres::Notification> for Notification {
+    fn from(n: tokio_postgres::Notification) -> Self {
+        Self {
+            process_id: n.process_id(),
+            channel: n.channel().to_string(),
+            payload: n.payload().to_string(),
+        }
+    }
+}
+
+/// A stream of [`Notification`]s for a `LISTEN`ed channel, backed by a
+/// dedicated connection kept out of the pool for the stream's lifetime.
+///
+/// The background task driving the connection is aborted when this value is
+/// dropped, so the `LISTEN` session ends along with it.
+pub struct NotificationStream {
+    receiver: mpsc::Receiver<Notification>,
+    handle: JoinHandle<()>,
+    // Kept alive only so the dedicated connection's request channel stays
+    // open for the lifetime of the stream; never queried directly.
+    _client: Client,
+}
+
+impl NotificationStream {
+    pub(crate) fn new(
+        receiver: mpsc::Receiver<Notification>,
+        handle: JoinHandle<()>,
+        client: Client,
+    ) -> Self {
+        Self {
+            receiver,
+            handle,
+            _client: client,
+        }
+    }
+}
+
+impl Stream for NotificationStream {
+    type Item = Notification;
+
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+impl Drop for NotificationStream {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+/// Drives a dedicated `tokio_postgres::Connection`, forwarding any
+/// `AsyncMessage::Notification` it observes into `sender` and dropping
+/// everything else (e.g. `AsyncMessage::Notice`).
+pub(crate) fn spawn_notification_listener<S, T>(
+    mut connection: tokio_postgres::Connection<S, T>,
+    sender: mpsc::Sender<Notification>,
+) -> JoinHandle<()>
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + 'static,
+    T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + 'static,
+{
+    tokio::spawn(async move {
+        let stream = futures::stream::poll_fn(move |cx| connection.poll_message(cx));
+        tokio::pin!(stream);
+
+        while let Some(message) = stream.next().await {
+            match message {
+                Ok(AsyncMessage::Notification(n)) => {
+                    if sender.send(n.into()).await.is_err() {
+                        break;
+                    }
+                }
+                Ok(_) => {}
+                Err(_) => break,
+            }
+        }
+    })
+}
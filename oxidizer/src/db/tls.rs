@@ -0,0 +1,67 @@
+use std::io::BufReader;
+
+use tokio_postgres_rustls::MakeRustlsConnect;
+
+use super::error::Error;
+
+/// How `DB::connect_with` should secure the connection to Postgres.
+///
+/// `OpenSsl` keeps the long-standing behaviour of loading a CA from a file
+/// on disk via the system OpenSSL. `Rustls` is for environments (musl,
+/// static binaries, policies that forbid linking OpenSSL) that need a pure
+/// Rust TLS stack instead, and takes its certificates as in-memory PEM
+/// rather than a path.
+pub enum TlsConfig {
+    Disabled,
+    OpenSsl {
+        ca_file: String,
+    },
+    Rustls {
+        ca_pem: Vec<u8>,
+        client_cert: Option<Vec<u8>>,
+        client_key: Option<Vec<u8>>,
+    },
+}
+
+pub(crate) fn build_rustls_connector(
+    ca_pem: &[u8],
+    client_cert: Option<&[u8]>,
+    client_key: Option<&[u8]>,
+) -> Result<MakeRustlsConnect, Error> {
+    let mut root_store = rustls::RootCertStore::empty();
+    for cert in rustls_pemfile::certs(&mut BufReader::new(ca_pem))
+        .map_err(|err| Error::TlsError(format!("invalid CA PEM: {}", err)))?
+    {
+        root_store
+            .add(&rustls::Certificate(cert))
+            .map_err(|err| Error::TlsError(format!("invalid CA certificate: {}", err)))?;
+    }
+
+    let builder = rustls::ClientConfig::builder()
+        .with_safe_defaults()
+        .with_root_certificates(root_store);
+
+    let config = match (client_cert, client_key) {
+        (Some(cert_pem), Some(key_pem)) => {
+            let certs = rustls_pemfile::certs(&mut BufReader::new(cert_pem))
+                .map_err(|err| Error::TlsError(format!("invalid client cert PEM: {}", err)))?
+                .into_iter()
+                .map(rustls::Certificate)
+                .collect();
+
+            let mut keys = rustls_pemfile::pkcs8_private_keys(&mut BufReader::new(key_pem))
+                .map_err(|err| Error::TlsError(format!("invalid client key PEM: {}", err)))?;
+
+            let key = rustls::PrivateKey(keys.pop().ok_or_else(|| {
+                Error::TlsError("no private key found in client key PEM".to_string())
+            })?);
+
+            builder
+                .with_client_auth_cert(certs, key)
+                .map_err(|err| Error::TlsError(format!("invalid client certificate: {}", err)))?
+        }
+        _ => builder.with_no_client_auth(),
+    };
+
+    Ok(MakeRustlsConnect::new(config))
+}
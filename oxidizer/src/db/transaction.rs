@@ -0,0 +1,158 @@
+use async_trait::async_trait;
+use postgres_openssl::MakeTlsConnector;
+use tokio_postgres::{row::Row, types::ToSql, Client, NoTls};
+use tokio_postgres_rustls::MakeRustlsConnect;
+
+use super::db::ConnectionManager;
+use super::error::*;
+use super::executor::Executor;
+
+/// A single pooled client checked out for the lifetime of a transaction.
+///
+/// Mirrors `ConnectionPool`'s per-TLS-backend variants so the pooled
+/// connection's concrete type is preserved without downcasting.
+pub(crate) enum TransactionClient {
+    TLS(mobc::Connection<ConnectionManager<MakeTlsConnector>>),
+    Rustls(mobc::Connection<ConnectionManager<MakeRustlsConnect>>),
+    NoTLS(mobc::Connection<ConnectionManager<NoTls>>),
+}
+
+impl std::ops::Deref for TransactionClient {
+    type Target = Client;
+
+    fn deref(&self) -> &Client {
+        match self {
+            TransactionClient::TLS(c) => &**c,
+            TransactionClient::Rustls(c) => &**c,
+            TransactionClient::NoTLS(c) => &**c,
+        }
+    }
+}
+
+/// A `BEGIN`/`COMMIT`/`ROLLBACK` transaction over a single pooled
+/// connection, returned by `DB::transaction`.
+///
+/// Dropping the transaction without calling [`Transaction::commit`] rolls
+/// it back, so an early return or a `?` partway through a multi-step
+/// operation can't leave a half-applied transaction open.
+pub struct Transaction {
+    client: Option<TransactionClient>,
+    finished: bool,
+}
+
+impl Transaction {
+    pub(crate) async fn begin(client: TransactionClient) -> Result<Self, Error> {
+        client
+            .batch_execute("BEGIN")
+            .await
+            .map_err(|err| Error::PostgresError(err))?;
+
+        Ok(Self {
+            client: Some(client),
+            finished: false,
+        })
+    }
+
+    fn client(&self) -> Result<&TransactionClient, Error> {
+        self.client.as_ref().ok_or(Error::Other)
+    }
+
+    pub async fn execute(
+        &self,
+        query: &str,
+        params: &'_ [&'_ (dyn ToSql + Sync)],
+    ) -> Result<u64, Error> {
+        let client = self.client()?;
+
+        let stmt = client
+            .prepare(query)
+            .await
+            .map_err(|err| Error::PostgresError(err))?;
+
+        client
+            .execute(&stmt, params)
+            .await
+            .map_err(|err| Error::PostgresError(err))
+    }
+
+    /// Runs `query` through the connection's `batch_execute`, which (unlike
+    /// [`Self::execute`]) accepts multiple `;`-separated statements in one
+    /// call.
+    pub async fn batch_execute(&self, query: &str) -> Result<(), Error> {
+        let client = self.client()?;
+
+        client
+            .batch_execute(query)
+            .await
+            .map_err(|err| Error::PostgresError(err))
+    }
+
+    pub async fn query(
+        &self,
+        query: &str,
+        params: &'_ [&'_ (dyn ToSql + Sync)],
+    ) -> Result<Vec<Row>, Error> {
+        let client = self.client()?;
+
+        let stmt = client
+            .prepare(query)
+            .await
+            .map_err(|err| Error::PostgresError(err))?;
+
+        client
+            .query(&stmt, params)
+            .await
+            .map_err(|err| Error::PostgresError(err))
+    }
+
+    pub async fn commit(mut self) -> Result<(), Error> {
+        let client = self.client.take().ok_or(Error::Other)?;
+
+        client
+            .batch_execute("COMMIT")
+            .await
+            .map_err(|err| Error::PostgresError(err))?;
+
+        self.finished = true;
+
+        Ok(())
+    }
+
+    pub async fn rollback(mut self) -> Result<(), Error> {
+        let client = self.client.take().ok_or(Error::Other)?;
+
+        client
+            .batch_execute("ROLLBACK")
+            .await
+            .map_err(|err| Error::PostgresError(err))?;
+
+        self.finished = true;
+
+        Ok(())
+    }
+}
+
+impl Drop for Transaction {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+
+        if let Some(client) = self.client.take() {
+            tokio::spawn(async move {
+                let _ = client.batch_execute("ROLLBACK").await;
+            });
+        }
+    }
+}
+
+#[async_trait]
+impl Executor for Transaction {
+    async fn execute(&self, query: &str, params: &'_ [&'_ (dyn ToSql + Sync)]) -> DBResult<u64> {
+        Transaction::execute(self, query, params).await
+    }
+
+    async fn query(&self, query: &str, params: &'_ [&'_ (dyn ToSql + Sync)]) -> DBResult<Vec<Row>> {
+        Transaction::query(self, query, params).await
+    }
+}
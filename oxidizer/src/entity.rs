@@ -0,0 +1,40 @@
+use async_trait::async_trait;
+use tokio_postgres::types::ToSql;
+use tokio_postgres::Row;
+
+use crate::db::{DBResult, DB};
+use crate::migration::Migration;
+
+/// Implemented by `#[derive(Entity)]` types to provide CRUD access backed by
+/// a Postgres table.
+#[async_trait]
+pub trait Entity: Sized {
+    async fn save(&mut self, db: &DB) -> DBResult<bool>;
+
+    /// Inserts every item in `items` in one multi-row `INSERT ... RETURNING`
+    /// per chunk (chunked to stay under Postgres's 65535 bind-parameter
+    /// cap), writing each row's generated primary key back in place.
+    ///
+    /// Every item must be unsaved (primary key equal to `Default::default()`)
+    /// — a mix of creates and updates is rejected rather than silently
+    /// splitting the work.
+    async fn save_many(db: &DB, items: &mut [Self]) -> DBResult<()>;
+
+    async fn delete(&mut self, db: &DB) -> DBResult<bool>;
+
+    fn from_row(row: &Row) -> Self;
+
+    async fn create_migration() -> DBResult<Migration>;
+
+    async fn find(
+        db: &DB,
+        condition: &str,
+        params: &'_ [&'_ (dyn ToSql + Sync)],
+    ) -> DBResult<Vec<Self>>;
+
+    async fn first(
+        db: &DB,
+        condition: &str,
+        params: &'_ [&'_ (dyn ToSql + Sync)],
+    ) -> DBResult<Option<Self>>;
+}
@@ -0,0 +1,8 @@
+pub mod db;
+pub mod entity;
+pub mod migration;
+
+pub use async_trait::async_trait;
+pub use migration::Migration;
+pub use tokio_postgres;
+pub use tokio_postgres::types as db_types;
@@ -0,0 +1,47 @@
+use barrel::{Migration as RawMigration, Table};
+
+/// A single schema migration, carrying both the forward (`up`) and the
+/// reverse (`down`) `barrel` migration body, under the name refinery will
+/// record it under.
+pub struct Migration {
+    pub name: String,
+    pub up: RawMigration,
+    pub down: RawMigration,
+}
+
+impl Migration {
+    pub fn new() -> Self {
+        Self {
+            name: "migration".to_string(),
+            up: RawMigration::new(),
+            down: RawMigration::new(),
+        }
+    }
+
+    pub fn create_table<F>(&mut self, name: &str, builder: F)
+    where
+        F: FnOnce(&mut Table),
+    {
+        self.name = format!("create_{}", name);
+        self.up.create_table(name, builder);
+        self.down.drop_table_if_exists(name);
+    }
+
+    /// Builds a standalone forward migration that drops `name`. Its `.down`
+    /// is left empty, so this is a one-off manual DROP to pass to
+    /// `DB::migrate_tables` directly — it is not a `down` counterpart to
+    /// pair with `create_table`'s own migration (that one already carries
+    /// its own drop in `.down`).
+    pub fn drop_table(name: &str) -> Self {
+        let mut m = Self::new();
+        m.name = format!("drop_{}", name);
+        m.up.drop_table_if_exists(name);
+        m
+    }
+}
+
+impl Default for Migration {
+    fn default() -> Self {
+        Self::new()
+    }
+}